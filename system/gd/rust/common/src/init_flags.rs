@@ -1,32 +1,169 @@
 use log::{error, info};
 use paste::paste;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
 use std::sync::Mutex;
 
+/// Expands to the type of a flag, which defaults to bool.
+macro_rules! type_expand {
+    () => {
+        bool
+    };
+    ($type:ty) => {
+        $type
+    };
+}
+
+/// Expands to the default value of a flag, which is either the declared
+/// default or the default value of the flag type.
+macro_rules! default_value {
+    () => {
+        false
+    };
+    ($type:ty) => {
+        <$type>::default()
+    };
+    (String, $default:expr) => {
+        String::from($default)
+    };
+    ($type:ty, $default:expr) => {
+        $default
+    };
+}
+
+/// Expands to the value set by `set_all_for_testing`: true for bool flags,
+/// a fixed non-default value for String and f64 flags, and the default
+/// value for other types.
+macro_rules! test_value {
+    () => {
+        true
+    };
+    (String $(, $default:expr)?) => {
+        String::from("test")
+    };
+    (f64 $(, $default:expr)?) => {
+        42.5
+    };
+    ($type:ty $(, $default:expr)?) => {
+        default_value!($type $(, $default)?)
+    };
+}
+
+/// Generates the accessor of a flag: `<flag>_is_enabled` for bool flags
+/// and `get_<flag>` for typed flags.
+macro_rules! create_getter_fn {
+    ($flag:ident) => {
+        paste! {
+            #[allow(missing_docs)]
+            pub fn [<$flag _is_enabled>]() -> bool {
+                FLAGS.lock().unwrap().$flag
+            }
+        }
+    };
+    ($flag:ident $type:ty) => {
+        paste! {
+            #[allow(missing_docs)]
+            pub fn [<get_ $flag>]() -> $type {
+                FLAGS.lock().unwrap().$flag.clone()
+            }
+        }
+    };
+}
+
+/// Declares an enum usable as an init flag type.
+///
+/// The enum parses from and displays as its variant names, and its first variant
+/// is the default. Parsing an unknown value fails with the list of allowed values.
+#[allow(unused_macros)]
+macro_rules! init_flag_enum {
+    ($(#[$attr:meta])* $name:ident { $first:ident $(, $variant:ident)* $(,)? }) => {
+        $(#[$attr])*
+        #[allow(missing_docs)]
+        #[derive(Clone, Copy, Debug, PartialEq, Eq)]
+        pub enum $name {
+            $first,
+            $($variant,)*
+        }
+
+        impl Default for $name {
+            fn default() -> Self {
+                $name::$first
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                match self {
+                    $name::$first => write!(f, stringify!($first)),
+                    $($name::$variant => write!(f, stringify!($variant)),)*
+                }
+            }
+        }
+
+        impl std::str::FromStr for $name {
+            type Err = String;
+
+            fn from_str(value: &str) -> Result<Self, Self::Err> {
+                match value {
+                    stringify!($first) => Ok($name::$first),
+                    $(stringify!($variant) => Ok($name::$variant),)*
+                    _ => Err(format!(
+                        "expected one of {}",
+                        [stringify!($first), $(stringify!($variant)),*].join(", ")
+                    )),
+                }
+            }
+        }
+    };
+}
+
+/// Declares the set of init flags and their accessors.
+///
+/// Flags are bool by default. A flag can also be declared with an explicit type
+/// implementing `FromStr`, `Display` and `Default`, and an optional default value,
+/// e.g. `name: String = "default"` or `ratio: f64 = 0.5`. Enum flag types are
+/// declared with `init_flag_enum!`.
 macro_rules! init_flags {
-    (flags: { $($flag:ident),* }, dependencies: { $($parent:ident => $child:ident),* }) => {
-        #[derive(Default)]
+    (flags: { $($flag:ident $(: $type:ident)? $(= $default:expr)?),* $(,)? },
+     dependencies: { $($parent:ident => $child:ident),* }) => {
         struct InitFlags {
-            $($flag: bool,)*
+            $($flag: type_expand!($($type)?),)*
+        }
+
+        impl Default for InitFlags {
+            fn default() -> Self {
+                Self { $($flag: default_value!($($type)? $(, $default)?),)* }
+            }
         }
 
-        /// Sets all flags to true, for testing
+        lazy_static! {
+            static ref FLAGS: Mutex<InitFlags> = Mutex::new(InitFlags::default());
+        }
+
+        /// Sets all bool flags to true, String and f64 flags to a non-default value,
+        /// and other flags to their default value, for testing
         pub fn set_all_for_testing() {
-            *FLAGS.lock().unwrap() = InitFlags { $($flag: true,)* };
+            *FLAGS.lock().unwrap() = InitFlags { $($flag: test_value!($($type)? $(, $default)?),)* };
         }
 
         impl InitFlags {
             fn parse(flags: Vec<String>) -> Self {
-                $(let mut $flag = false;)*
+                $(let mut $flag: type_expand!($($type)?) = default_value!($($type)? $(, $default)?);)*
 
                 for flag in flags {
-                    let values: Vec<&str> = flag.split("=").collect();
+                    let values: Vec<&str> = flag.splitn(2, "=").collect();
                     if values.len() != 2 {
                         error!("Bad flag {}, must be in <FLAG>=<VALUE> format", flag);
                         continue;
                     }
 
                     match values[0] {
-                        $(concat!("INIT_", stringify!($flag)) => $flag = values[1].parse().unwrap_or(false),)*
+                        $(concat!("INIT_", stringify!($flag)) => {
+                            $flag = values[1].parse().unwrap_or_else(|err| {
+                                error!("Bad value {} for flag {}: {}", values[1], values[0], err);
+                                default_value!($($type)? $(, $default)?)
+                            })
+                        },)*
                         _ => {}
                     }
                 }
@@ -34,6 +171,7 @@ macro_rules! init_flags {
                 Self { $($flag,)* }.reconcile()
             }
 
+            #[allow(unused_mut)]
             fn reconcile(mut self) -> Self {
                 // Loop to ensure dependencies can be specified in any order
                 loop {
@@ -48,26 +186,57 @@ macro_rules! init_flags {
                     }
                 }
 
-                // TODO: acl should not be off if l2cap is on, but need to reconcile legacy code
-                if self.gd_l2cap {
-                  // TODO This can never be turned off  self.gd_acl = false;
-                }
-
                 self
             }
 
             fn log(&self) {
-                info!(concat!("Flags loaded: ", $(stringify!($flag), "={} ",)*), $(self.$flag,)*);
+                info!("Flags loaded: {}", self);
+            }
+
+            fn values(&self) -> BTreeMap<&'static str, String> {
+                let mut values = BTreeMap::new();
+                $(values.insert(stringify!($flag), self.$flag.to_string());)*
+                values
             }
         }
 
-        paste! {
-            $(
-                #[allow(missing_docs)]
-                pub fn [<$flag _is_enabled>]() -> bool {
-                    FLAGS.lock().unwrap().$flag
+        impl fmt::Display for InitFlags {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, concat!($(stringify!($flag), "={} ",)*), $(self.$flag,)*)
+            }
+        }
+
+        $(create_getter_fn!($flag $($type)?);)*
+
+        #[cfg(test)]
+        mod tests_autogenerated {
+            use super::*;
+
+            $(paste! {
+                #[test]
+                fn [<test_parse_ $flag>]() {
+                    let value: type_expand!($($type)?) = test_value!($($type)? $(, $default)?);
+                    // The test value may be the default, which parse also falls back to.
+                    let text = value.to_string();
+                    assert_eq!(text.parse::<type_expand!($($type)?)>().ok(), Some(value.clone()));
+                    let flags =
+                        InitFlags::parse(vec![format!("INIT_{}={}", stringify!($flag), text)]);
+                    assert_eq!(flags.$flag, value);
                 }
-            )*
+
+                #[test]
+                fn [<test_parse_bad_value_ $flag>]() {
+                    let value = "bad_value";
+                    let flags =
+                        InitFlags::parse(vec![format!("INIT_{}={}", stringify!($flag), value)]);
+                    // Values of some types, like String, cannot be invalid.
+                    if value.parse::<type_expand!($($type)?)>().is_err() {
+                        let default: type_expand!($($type)?) =
+                            default_value!($($type)? $(, $default)?);
+                        assert_eq!(flags.$flag, default);
+                    }
+                }
+            })*
         }
     };
 }
@@ -84,12 +253,22 @@ init_flags!(
         gd_link_policy
     },
     dependencies: {
+        // TODO: acl should not be off if l2cap is on, but need to reconcile legacy code
         gd_core => gd_security
     }
 );
 
+type FlagObserver = Box<dyn Fn(&str) + Send>;
+
+#[derive(Default)]
+struct FlagObservers {
+    next_id: u32,
+    observers: HashMap<u32, (String, FlagObserver)>,
+}
+
 lazy_static! {
-    static ref FLAGS: Mutex<InitFlags> = Mutex::new(InitFlags::default());
+    // Kept separate from FLAGS so observers can read flags when notified.
+    static ref OBSERVERS: Mutex<FlagObservers> = Mutex::new(FlagObservers::default());
 }
 
 /// Loads the flag values from the passed-in vector of string values
@@ -98,5 +277,204 @@ pub fn load(flags: Vec<String>) {
 
     let flags = InitFlags::parse(flags);
     flags.log();
-    *FLAGS.lock().unwrap() = flags;
+    let new_values = flags.values();
+    let old_values = std::mem::replace(&mut *FLAGS.lock().unwrap(), flags).values();
+
+    let observers = OBSERVERS.lock().unwrap();
+    for (name, callback) in observers.observers.values() {
+        match new_values.get(name.as_str()) {
+            Some(value) if old_values.get(name.as_str()) != Some(value) => callback(value),
+            _ => (),
+        }
+    }
+}
+
+/// Registers a callback invoked with the new value of the flag `name` whenever
+/// `load` changes it. The callback must not register or unregister observers.
+///
+/// Returns an identifier to pass to `unregister_flag_observer`.
+pub fn register_flag_observer(name: &str, callback: impl Fn(&str) + Send + 'static) -> u32 {
+    let mut observers = OBSERVERS.lock().unwrap();
+    let id = observers.next_id;
+    observers.next_id += 1;
+    observers.observers.insert(id, (name.to_string(), Box::new(callback)));
+    id
+}
+
+/// Removes the flag observer identified by `id`.
+///
+/// Returns false if `id` is not recognized.
+pub fn unregister_flag_observer(id: u32) -> bool {
+    OBSERVERS.lock().unwrap().observers.remove(&id).is_some()
+}
+
+/// Returns the current value of each flag, keyed by flag name.
+pub fn dump() -> BTreeMap<&'static str, String> {
+    FLAGS.lock().unwrap().values()
+}
+
+/// Returns the current flag values as a JSON object with sorted keys.
+pub fn dump_json() -> String {
+    values_to_json(&dump())
+}
+
+fn values_to_json(values: &BTreeMap<&'static str, String>) -> String {
+    let members: Vec<String> = values
+        .iter()
+        .map(|(name, value)| format!("{}:{}", json_string(name), json_string(value)))
+        .collect();
+    format!("{{{}}}", members.join(","))
+}
+
+fn json_string(value: &str) -> String {
+    let mut json = String::with_capacity(value.len() + 2);
+    json.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => json.push_str("\\\""),
+            '\\' => json.push_str("\\\\"),
+            '\n' => json.push_str("\\n"),
+            '\r' => json.push_str("\\r"),
+            '\t' => json.push_str("\\t"),
+            c if (c as u32) < 0x20 => json.push_str(&format!("\\u{:04x}", c as u32)),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_dump_json() {
+        // Other tests load flags concurrently, so take a single snapshot.
+        let values = dump();
+        let json = values_to_json(&values);
+        let parsed: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_str(&json).expect("invalid JSON");
+        assert_eq!(parsed.len(), values.len());
+        for (name, value) in &values {
+            assert_eq!(json.matches(&format!("\"{}\":", name)).count(), 1);
+            assert_eq!(parsed[*name], serde_json::Value::String(value.clone()));
+        }
+    }
+
+    #[test]
+    fn test_flag_observer() {
+        let rust_values = Arc::new(Mutex::new(vec![]));
+        let core_values = Arc::new(Mutex::new(vec![]));
+        let values = rust_values.clone();
+        let rust_id =
+            register_flag_observer("gd_rust", move |v| values.lock().unwrap().push(v.to_string()));
+        let values = core_values.clone();
+        let core_id =
+            register_flag_observer("gd_core", move |v| values.lock().unwrap().push(v.to_string()));
+
+        load(vec!["INIT_gd_rust=true".to_string()]);
+        load(vec!["INIT_gd_rust=true".to_string()]);
+        load(vec![]);
+        assert_eq!(*rust_values.lock().unwrap(), vec!["true", "false"]);
+        assert!(core_values.lock().unwrap().is_empty());
+
+        assert!(unregister_flag_observer(rust_id));
+        assert!(!unregister_flag_observer(rust_id));
+        load(vec!["INIT_gd_rust=true".to_string()]);
+        load(vec![]);
+        assert_eq!(rust_values.lock().unwrap().len(), 2);
+        assert!(unregister_flag_observer(core_id));
+    }
+
+    mod typed {
+        #![allow(dead_code)]
+        use super::super::*;
+
+        init_flag_enum!(Mode { First, Second, Third });
+
+        init_flags!(
+            flags: {
+                bool_flag,
+                string_flag: String,
+                string_flag_with_default: String = "default",
+                float_flag: f64,
+                float_flag_with_default: f64 = -1.5,
+                enum_flag: Mode,
+                enum_flag_with_default: Mode = Mode::Second
+            },
+            dependencies: {}
+        );
+
+        #[test]
+        fn test_defaults() {
+            let flags = InitFlags::parse(vec![]);
+            assert!(!flags.bool_flag);
+            assert_eq!(flags.string_flag, "");
+            assert_eq!(flags.string_flag_with_default, "default");
+            assert_eq!(flags.float_flag, 0.0);
+            assert_eq!(flags.float_flag_with_default, -1.5);
+            assert_eq!(flags.enum_flag, Mode::First);
+            assert_eq!(flags.enum_flag_with_default, Mode::Second);
+        }
+
+        #[test]
+        fn test_bad_value_falls_back_to_default() {
+            let flags = InitFlags::parse(vec![
+                "INIT_float_flag_with_default=abc".to_string(),
+                "INIT_enum_flag_with_default=Fourth".to_string(),
+            ]);
+            assert_eq!(flags.float_flag_with_default, -1.5);
+            assert_eq!(flags.enum_flag_with_default, Mode::Second);
+        }
+
+        #[test]
+        fn test_values_to_json() {
+            let flags = InitFlags::parse(vec!["INIT_string_flag=a\"b\\c\nd".to_string()]);
+            assert_eq!(
+                values_to_json(&flags.values()),
+                "{\"bool_flag\":\"false\",\"enum_flag\":\"First\",\
+                 \"enum_flag_with_default\":\"Second\",\"float_flag\":\"0\",\
+                 \"float_flag_with_default\":\"-1.5\",\"string_flag\":\"a\\\"b\\\\c\\nd\",\
+                 \"string_flag_with_default\":\"default\"}"
+            );
+        }
+
+        #[test]
+        fn test_values_to_json_escaping() {
+            let value = "\"quoted\" \\ \n\r\t\u{1} \u{e9}";
+            let flags = InitFlags::parse(vec![format!("INIT_string_flag={}", value)]);
+            let parsed: serde_json::Value =
+                serde_json::from_str(&values_to_json(&flags.values())).expect("invalid JSON");
+            assert_eq!(parsed["string_flag"], value);
+        }
+
+        #[test]
+        fn test_enum_value() {
+            let flags = InitFlags::parse(vec!["INIT_enum_flag=Third".to_string()]);
+            assert_eq!(flags.enum_flag, Mode::Third);
+            assert_eq!(
+                "Fourth".parse::<Mode>(),
+                Err("expected one of First, Second, Third".to_string())
+            );
+        }
+
+        #[test]
+        fn test_string_value_with_separator() {
+            let flags = InitFlags::parse(vec!["INIT_string_flag=a=b".to_string()]);
+            assert_eq!(flags.string_flag, "a=b");
+        }
+
+        #[test]
+        fn test_display() {
+            let flags = InitFlags::parse(vec!["INIT_string_flag=name".to_string()]);
+            assert_eq!(
+                flags.to_string(),
+                "bool_flag=false string_flag=name string_flag_with_default=default \
+                 float_flag=0 float_flag_with_default=-1.5 enum_flag=First \
+                 enum_flag_with_default=Second "
+            );
+        }
+    }
 }
@@ -0,0 +1,3 @@
+//! Code generation backends.
+
+pub mod c;
@@ -0,0 +1,1006 @@
+//! C backend for the PDL tool.
+//!
+//! Generates a self-contained C header declaring a struct and
+//! `parse`/`size`/`serialize` functions for each packet and struct
+//! declaration, plus a validity check for each enum declaration.
+//!
+//! Consecutive bit fields are grouped into chunks, which are read and
+//! written as a single integer in the grammar endianness. Within a
+//! chunk, the first field occupies the least significant bits.
+//! Variable sized arrays and payloads are not copied: the parsed
+//! struct references the input buffer. Variable sized arrays of
+//! elements wider than a byte additionally have a `<array>_values`
+//! member, which can be set to host order elements to serialize
+//! instead of the encoded bytes.
+
+use crate::ast;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Preamble of the generated header.
+const PREAMBLE: &str = "\
+// File generated by the PDL C backend.
+// /!\\ Do not edit by hand.
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+";
+
+/// Integer accessors for little endian grammars.
+const LE_HELPERS: &str = "
+#ifndef PDL_UINT_LE_HELPERS
+#define PDL_UINT_LE_HELPERS
+
+static inline uint64_t pdl_get_uint_le(const uint8_t *buf, size_t n) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < n; i++) {
+        value |= (uint64_t)buf[i] << (8 * i);
+    }
+    return value;
+}
+
+static inline void pdl_put_uint_le(uint8_t *buf, size_t n, uint64_t value) {
+    for (size_t i = 0; i < n; i++) {
+        buf[i] = (uint8_t)(value >> (8 * i));
+    }
+}
+
+#endif  // PDL_UINT_LE_HELPERS
+";
+
+/// Integer accessors for big endian grammars.
+const BE_HELPERS: &str = "
+#ifndef PDL_UINT_BE_HELPERS
+#define PDL_UINT_BE_HELPERS
+
+static inline uint64_t pdl_get_uint_be(const uint8_t *buf, size_t n) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < n; i++) {
+        value = (value << 8) | buf[i];
+    }
+    return value;
+}
+
+static inline void pdl_put_uint_be(uint8_t *buf, size_t n, uint64_t value) {
+    for (size_t i = 0; i < n; i++) {
+        buf[n - 1 - i] = (uint8_t)(value >> (8 * i));
+    }
+}
+
+#endif  // PDL_UINT_BE_HELPERS
+";
+
+/// Layout element of a packet or struct declaration.
+enum Item<'d> {
+    /// Group of bit fields with a total width multiple of 8,
+    /// stored as `(shift, field)` pairs.
+    Chunk { bytes: usize, fields: Vec<(usize, &'d ast::Field)> },
+    /// Byte aligned array or payload field.
+    Bytes(&'d ast::Field),
+}
+
+/// Source of the length of a variable sized field.
+enum Len {
+    /// Element count given by a `_count_` field.
+    Count,
+    /// Byte size given by a `_size_` field.
+    Size,
+    /// The field extends to the end of the packet.
+    Remaining,
+}
+
+struct Generator<'d> {
+    /// Suffix of the integer accessors: `le` or `be`.
+    endianness: &'static str,
+    /// Enum declarations, with their width.
+    enums: HashMap<&'d str, usize>,
+}
+
+/// Return the smallest C unsigned integer type holding `width` bits.
+fn c_type(width: usize) -> &'static str {
+    match width {
+        0..=8 => "uint8_t",
+        9..=16 => "uint16_t",
+        17..=32 => "uint32_t",
+        _ => "uint64_t",
+    }
+}
+
+/// Return the width of the C type holding `width` bits.
+fn c_type_width(width: usize) -> usize {
+    match width {
+        0..=8 => 8,
+        9..=16 => 16,
+        17..=32 => 32,
+        _ => 64,
+    }
+}
+
+fn max_value(width: usize) -> u64 {
+    if width >= 64 {
+        u64::MAX
+    } else {
+        (1 << width) - 1
+    }
+}
+
+/// Name used for the members and locals of a sized field.
+/// `_payload_` and `_body_` are mapped to `payload` and `body`.
+fn sized_name(field_id: &str) -> &str {
+    field_id.trim_matches('_')
+}
+
+impl<'d> Generator<'d> {
+    /// Return the bit width of a field that can be part of a chunk,
+    /// or None for byte aligned fields.
+    fn bit_width(&self, decl_id: &str, field: &ast::Field) -> Result<Option<usize>, String> {
+        match field {
+            ast::Field::Scalar { width, .. }
+            | ast::Field::Reserved { width, .. }
+            | ast::Field::Size { width, .. }
+            | ast::Field::Count { width, .. }
+            | ast::Field::Fixed { width: Some(width), .. } => Ok(Some(*width)),
+            ast::Field::Fixed { enum_id: Some(enum_id), .. } => {
+                self.enum_width(decl_id, enum_id).map(Some)
+            }
+            ast::Field::Typedef { type_id, .. } => self.enum_width(decl_id, type_id).map(Some),
+            ast::Field::Array { .. } | ast::Field::Payload { .. } | ast::Field::Body { .. } => {
+                Ok(None)
+            }
+            _ => Err(format!("{}: unsupported field at {}", decl_id, field.loc())),
+        }
+    }
+
+    fn enum_width(&self, decl_id: &str, type_id: &str) -> Result<usize, String> {
+        self.enums.get(type_id).copied().ok_or_else(|| {
+            format!("{}: `{}` is not an enum, only enum typedefs are supported", decl_id, type_id)
+        })
+    }
+
+    /// Split the fields of a declaration into chunks and byte aligned fields.
+    fn layout(&self, id: &str, fields: &'d [ast::Field]) -> Result<Vec<Item<'d>>, String> {
+        let mut items = vec![];
+        let mut chunk = vec![];
+        let mut chunk_width = 0;
+        for field in fields {
+            match self.bit_width(id, field)? {
+                Some(width) => {
+                    chunk.push((chunk_width, field));
+                    chunk_width += width;
+                    if chunk_width > 64 {
+                        return Err(format!(
+                            "{}: bit fields ending at {} span more than 64 bits",
+                            id,
+                            field.loc()
+                        ));
+                    }
+                    if chunk_width % 8 == 0 {
+                        items.push(Item::Chunk {
+                            bytes: chunk_width / 8,
+                            fields: std::mem::take(&mut chunk),
+                        });
+                        chunk_width = 0;
+                    }
+                }
+                None if chunk_width != 0 => {
+                    return Err(format!("{}: field at {} is not byte aligned", id, field.loc()))
+                }
+                None => items.push(Item::Bytes(field)),
+            }
+        }
+        if chunk_width != 0 {
+            return Err(format!("{}: declaration is not byte aligned", id));
+        }
+        Ok(items)
+    }
+
+    /// Return the element byte size of an array field.
+    fn element_bytes(id: &str, field: &ast::Field) -> Result<usize, String> {
+        match field {
+            ast::Field::Array { width: Some(width), size_modifier: None, .. }
+                if width % 8 == 0 && *width <= 64 =>
+            {
+                Ok(width / 8)
+            }
+            _ => Err(format!(
+                "{}: unsupported array at {}, only unmodified arrays of byte aligned scalars are supported",
+                id,
+                field.loc()
+            )),
+        }
+    }
+
+    fn generate_enum(&self, out: &mut String, id: &str, tags: &[ast::Tag]) {
+        writeln!(out).unwrap();
+        writeln!(out, "typedef enum {{").unwrap();
+        for tag in tags {
+            writeln!(out, "    {}_{} = {:#x},", id, tag.id, tag.value).unwrap();
+        }
+        writeln!(out, "}} {};", id).unwrap();
+        writeln!(out).unwrap();
+        writeln!(out, "static inline bool {}_is_valid(uint64_t value) {{", id).unwrap();
+        writeln!(out, "    switch (value) {{").unwrap();
+        for tag in tags {
+            writeln!(out, "    case {:#x}:", tag.value).unwrap();
+        }
+        if !tags.is_empty() {
+            writeln!(out, "        return true;").unwrap();
+        }
+        writeln!(out, "    default:").unwrap();
+        writeln!(out, "        return false;").unwrap();
+        writeln!(out, "    }}").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    fn generate_packet(
+        &self,
+        out: &mut String,
+        id: &str,
+        fields: &'d [ast::Field],
+    ) -> Result<(), String> {
+        let items = self.layout(id, fields)?;
+
+        // Gather the length source of each variable sized field.
+        let mut lengths = HashMap::new();
+        for field in fields {
+            match field {
+                ast::Field::Count { field_id, .. } => {
+                    lengths.insert(field_id.as_str(), Len::Count);
+                }
+                ast::Field::Size { field_id, .. } => {
+                    lengths.insert(field_id.as_str(), Len::Size);
+                }
+                _ => (),
+            }
+        }
+
+        // Element byte size of the targets of count and size fields.
+        let mut targets = HashMap::new();
+        for (index, field) in fields.iter().enumerate() {
+            let (name, target_id, bytes) = match field {
+                ast::Field::Array { id: array_id, size: None, .. } => {
+                    (array_id.as_str(), array_id.as_str(), Self::element_bytes(id, field)?)
+                }
+                ast::Field::Array { id: array_id, .. } => {
+                    Self::element_bytes(id, field)?;
+                    if lengths.contains_key(array_id.as_str()) {
+                        return Err(format!(
+                            "{}: fixed size array `{}` cannot have a size or count field",
+                            id, array_id
+                        ));
+                    }
+                    continue;
+                }
+                ast::Field::Payload { size_modifier: Some(_), .. } => {
+                    return Err(format!("{}: payload size modifiers are not supported", id))
+                }
+                ast::Field::Payload { .. } => ("payload", "_payload_", 1),
+                ast::Field::Body { .. } => ("body", "_body_", 1),
+                _ => continue,
+            };
+            match lengths.get(target_id) {
+                Some(Len::Count) if target_id.starts_with('_') => {
+                    return Err(format!("{}: `{}` cannot have a count field", id, target_id))
+                }
+                Some(_) => (),
+                None if index + 1 == fields.len() => (),
+                None => {
+                    return Err(format!(
+                        "{}: `{}` must be the last field, or have a size or count field",
+                        id, name
+                    ))
+                }
+            }
+            targets.insert(target_id, bytes);
+        }
+        for (index, field) in fields.iter().enumerate() {
+            if let ast::Field::Size { field_id, .. } | ast::Field::Count { field_id, .. } = field {
+                let declared_after = fields[index..].iter().any(|f| match f {
+                    ast::Field::Payload { .. } => field_id == "_payload_",
+                    ast::Field::Body { .. } => field_id == "_body_",
+                    _ => f.id() == Some(field_id),
+                });
+                if !targets.contains_key(field_id.as_str()) || !declared_after {
+                    return Err(format!(
+                        "{}: size or count field at {} must precede a variable sized array or payload",
+                        id,
+                        field.loc()
+                    ));
+                }
+            }
+        }
+
+        self.generate_struct(out, id, fields);
+        self.generate_parse(out, id, &items, &lengths);
+        self.generate_size(out, id, &items);
+        self.generate_accessors(out, id, fields);
+        self.generate_serialize(out, id, &items, &targets);
+        Ok(())
+    }
+
+    fn generate_struct(&self, out: &mut String, id: &str, fields: &[ast::Field]) {
+        let mut members = vec![];
+        for field in fields {
+            match field {
+                ast::Field::Scalar { id, width, .. } => {
+                    members.push(format!("{} {};", c_type(*width), id))
+                }
+                ast::Field::Typedef { id, type_id, .. } => {
+                    members.push(format!("{} {};", type_id, id))
+                }
+                ast::Field::Array { id, width: Some(width), size: Some(size), .. } => {
+                    members.push(format!("{} {}[{}];", c_type(*width), id, size))
+                }
+                ast::Field::Array { id, width: Some(width), .. } if *width > 8 => {
+                    members.push(format!("const uint8_t *{};", id));
+                    members.push(format!("const {} *{}_values;", c_type(*width), id));
+                    members.push(format!("size_t {}_count;", id));
+                }
+                ast::Field::Array { id, .. } => {
+                    members.push(format!("const uint8_t *{};", id));
+                    members.push(format!("size_t {}_count;", id));
+                }
+                ast::Field::Payload { .. } | ast::Field::Body { .. } => {
+                    let name = if matches!(field, ast::Field::Payload { .. }) {
+                        "payload"
+                    } else {
+                        "body"
+                    };
+                    members.push(format!("const uint8_t *{};", name));
+                    members.push(format!("size_t {}_size;", name));
+                }
+                _ => (),
+            }
+        }
+        if members.is_empty() {
+            // C does not allow empty structs.
+            members.push("uint8_t _unused;".to_owned());
+        }
+
+        writeln!(out).unwrap();
+        writeln!(out, "typedef struct {{").unwrap();
+        for member in members {
+            writeln!(out, "    {}", member).unwrap();
+        }
+        writeln!(out, "}} {};", id).unwrap();
+    }
+
+    /// Return the expression extracting a field from a chunk.
+    fn chunk_value(chunk: &str, shift: usize, width: usize, chunk_width: usize) -> String {
+        let shifted =
+            if shift == 0 { chunk.to_owned() } else { format!("({} >> {})", chunk, shift) };
+        if shift + width == chunk_width && width != 0 {
+            shifted
+        } else {
+            format!("({} & {:#x})", shifted, max_value(width))
+        }
+    }
+
+    fn generate_parse(
+        &self,
+        out: &mut String,
+        id: &str,
+        items: &[Item],
+        lengths: &HashMap<&str, Len>,
+    ) {
+        let e = self.endianness;
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "static inline bool {}_parse(const uint8_t *buf, size_t len, {} *out) {{",
+            id, id
+        )
+        .unwrap();
+        if items.is_empty() {
+            writeln!(out, "    (void)buf;").unwrap();
+            writeln!(out, "    (void)out;").unwrap();
+            writeln!(out, "    return len == 0;").unwrap();
+            writeln!(out, "}}").unwrap();
+            return;
+        }
+
+        writeln!(out, "    size_t offset = 0;").unwrap();
+        for (index, item) in items.iter().enumerate() {
+            match item {
+                Item::Chunk { bytes, fields } => {
+                    let chunk = format!("chunk{}", index);
+                    let chunk_width = bytes * 8;
+                    writeln!(out, "    if (len - offset < {}) {{", bytes).unwrap();
+                    writeln!(out, "        return false;").unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    if fields.iter().any(|(_, f)| !matches!(f, ast::Field::Reserved { .. })) {
+                        writeln!(
+                            out,
+                            "    uint64_t {} = pdl_get_uint_{}(buf + offset, {});",
+                            chunk, e, bytes
+                        )
+                        .unwrap();
+                    }
+                    for (shift, field) in fields {
+                        let width = self.bit_width(id, field).unwrap().unwrap();
+                        let value = Self::chunk_value(&chunk, *shift, width, chunk_width);
+                        match field {
+                            ast::Field::Scalar { id, width, .. } => {
+                                writeln!(out, "    out->{} = ({}){};", id, c_type(*width), value)
+                                    .unwrap()
+                            }
+                            ast::Field::Typedef { id, type_id, .. } => {
+                                writeln!(out, "    if (!{}_is_valid({})) {{", type_id, value)
+                                    .unwrap();
+                                writeln!(out, "        return false;").unwrap();
+                                writeln!(out, "    }}").unwrap();
+                                writeln!(out, "    out->{} = ({}){};", id, type_id, value).unwrap();
+                            }
+                            ast::Field::Fixed { value: Some(fixed), .. } => {
+                                writeln!(out, "    if ({} != {:#x}) {{", value, fixed).unwrap();
+                                writeln!(out, "        return false;").unwrap();
+                                writeln!(out, "    }}").unwrap();
+                            }
+                            ast::Field::Fixed {
+                                enum_id: Some(enum_id),
+                                tag_id: Some(tag_id),
+                                ..
+                            } => {
+                                writeln!(out, "    if ({} != {}_{}) {{", value, enum_id, tag_id)
+                                    .unwrap();
+                                writeln!(out, "        return false;").unwrap();
+                                writeln!(out, "    }}").unwrap();
+                            }
+                            ast::Field::Count { field_id, .. } => writeln!(
+                                out,
+                                "    size_t {}_count = (size_t){};",
+                                sized_name(field_id),
+                                value
+                            )
+                            .unwrap(),
+                            ast::Field::Size { field_id, .. } => writeln!(
+                                out,
+                                "    size_t {}_size = (size_t){};",
+                                sized_name(field_id),
+                                value
+                            )
+                            .unwrap(),
+                            _ => (),
+                        }
+                    }
+                    writeln!(out, "    offset += {};", bytes).unwrap();
+                }
+                Item::Bytes(ast::Field::Array {
+                    id: array_id,
+                    width: Some(width),
+                    size: Some(size),
+                    ..
+                }) => {
+                    let bytes = width / 8;
+                    writeln!(out, "    if (len - offset < {}) {{", size * bytes).unwrap();
+                    writeln!(out, "        return false;").unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out, "    for (size_t i = 0; i < {}; i++) {{", size).unwrap();
+                    writeln!(
+                        out,
+                        "        out->{}[i] = ({})pdl_get_uint_{}(buf + offset + i * {}, {});",
+                        array_id,
+                        c_type(*width),
+                        e,
+                        bytes,
+                        bytes
+                    )
+                    .unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out, "    offset += {};", size * bytes).unwrap();
+                }
+                Item::Bytes(field) => {
+                    let (name, target_id, bytes, count_member) = match field {
+                        ast::Field::Array { id: array_id, width: Some(width), .. } => {
+                            (array_id.as_str(), array_id.as_str(), width / 8, "count")
+                        }
+                        ast::Field::Payload { .. } => ("payload", "_payload_", 1, "size"),
+                        ast::Field::Body { .. } => ("body", "_body_", 1, "size"),
+                        _ => unreachable!(),
+                    };
+                    match lengths.get(target_id).unwrap_or(&Len::Remaining) {
+                        Len::Count => {
+                            writeln!(
+                                out,
+                                "    if ({}_count > (len - offset) / {}) {{",
+                                name, bytes
+                            )
+                            .unwrap();
+                            writeln!(out, "        return false;").unwrap();
+                            writeln!(out, "    }}").unwrap();
+                            writeln!(out, "    out->{} = buf + offset;", name).unwrap();
+                            writeln!(out, "    out->{}_count = {}_count;", name, name).unwrap();
+                            writeln!(out, "    offset += {}_count * {};", name, bytes).unwrap();
+                        }
+                        Len::Size => {
+                            if bytes == 1 {
+                                writeln!(out, "    if ({}_size > len - offset) {{", name).unwrap();
+                            } else {
+                                writeln!(
+                                    out,
+                                    "    if ({}_size > len - offset || {}_size % {} != 0) {{",
+                                    name, name, bytes
+                                )
+                                .unwrap();
+                            }
+                            writeln!(out, "        return false;").unwrap();
+                            writeln!(out, "    }}").unwrap();
+                            writeln!(out, "    out->{} = buf + offset;", name).unwrap();
+                            if bytes == 1 {
+                                writeln!(
+                                    out,
+                                    "    out->{}_{} = {}_size;",
+                                    name, count_member, name
+                                )
+                                .unwrap();
+                            } else {
+                                writeln!(
+                                    out,
+                                    "    out->{}_{} = {}_size / {};",
+                                    name, count_member, name, bytes
+                                )
+                                .unwrap();
+                            }
+                            writeln!(out, "    offset += {}_size;", name).unwrap();
+                        }
+                        Len::Remaining => {
+                            if bytes != 1 {
+                                writeln!(out, "    if ((len - offset) % {} != 0) {{", bytes)
+                                    .unwrap();
+                                writeln!(out, "        return false;").unwrap();
+                                writeln!(out, "    }}").unwrap();
+                            }
+                            writeln!(out, "    out->{} = buf + offset;", name).unwrap();
+                            if bytes == 1 {
+                                writeln!(out, "    out->{}_{} = len - offset;", name, count_member)
+                                    .unwrap();
+                            } else {
+                                writeln!(
+                                    out,
+                                    "    out->{}_{} = (len - offset) / {};",
+                                    name, count_member, bytes
+                                )
+                                .unwrap();
+                            }
+                            writeln!(out, "    offset = len;").unwrap();
+                        }
+                    }
+                    if bytes != 1 {
+                        writeln!(out, "    out->{}_values = NULL;", name).unwrap();
+                    }
+                }
+            }
+        }
+        writeln!(out, "    return offset == len;").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    fn generate_size(&self, out: &mut String, id: &str, items: &[Item]) {
+        let mut fixed = 0;
+        let mut variable = vec![];
+        for item in items {
+            match item {
+                Item::Chunk { bytes, .. } => fixed += bytes,
+                Item::Bytes(ast::Field::Array { width: Some(width), size: Some(size), .. }) => {
+                    fixed += size * width / 8
+                }
+                Item::Bytes(ast::Field::Array { id, width: Some(8), .. }) => {
+                    variable.push(format!("p->{}_count", id))
+                }
+                Item::Bytes(ast::Field::Array { id, width: Some(width), .. }) => {
+                    variable.push(format!("p->{}_count * {}", id, width / 8))
+                }
+                Item::Bytes(ast::Field::Payload { .. }) => {
+                    variable.push("p->payload_size".to_owned())
+                }
+                Item::Bytes(ast::Field::Body { .. }) => variable.push("p->body_size".to_owned()),
+                Item::Bytes(_) => unreachable!(),
+            }
+        }
+
+        writeln!(out).unwrap();
+        writeln!(out, "static inline size_t {}_size(const {} *p) {{", id, id).unwrap();
+        if variable.is_empty() {
+            writeln!(out, "    (void)p;").unwrap();
+            writeln!(out, "    return {};", fixed).unwrap();
+        } else {
+            if fixed != 0 {
+                variable.insert(0, fixed.to_string());
+            }
+            writeln!(out, "    return {};", variable.join(" + ")).unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+
+    fn generate_serialize(
+        &self,
+        out: &mut String,
+        id: &str,
+        items: &[Item],
+        targets: &HashMap<&str, usize>,
+    ) {
+        let e = self.endianness;
+        writeln!(out).unwrap();
+        writeln!(
+            out,
+            "static inline bool {}_serialize(const {} *p, uint8_t *buf, size_t len) {{",
+            id, id
+        )
+        .unwrap();
+        if items.is_empty() {
+            writeln!(out, "    (void)p;").unwrap();
+            writeln!(out, "    (void)buf;").unwrap();
+            writeln!(out, "    (void)len;").unwrap();
+            writeln!(out, "    return true;").unwrap();
+            writeln!(out, "}}").unwrap();
+            return;
+        }
+
+        writeln!(out, "    size_t offset = 0;").unwrap();
+        writeln!(out, "    if (len < {}_size(p)) {{", id).unwrap();
+        writeln!(out, "        return false;").unwrap();
+        writeln!(out, "    }}").unwrap();
+        for item in items {
+            match item {
+                Item::Chunk { bytes, fields } => {
+                    let mut values = vec![];
+                    for (shift, field) in fields {
+                        let width = self.bit_width(id, field).unwrap().unwrap();
+                        let (value, checked) = match field {
+                            ast::Field::Scalar { id, .. } => {
+                                (format!("p->{}", id), width < c_type_width(width))
+                            }
+                            ast::Field::Typedef { id, type_id, .. } => {
+                                writeln!(out, "    if (!{}_is_valid(p->{})) {{", type_id, id)
+                                    .unwrap();
+                                writeln!(out, "        return false;").unwrap();
+                                writeln!(out, "    }}").unwrap();
+                                (format!("p->{}", id), false)
+                            }
+                            ast::Field::Fixed { value: Some(fixed), .. } => {
+                                (format!("{:#x}", fixed), false)
+                            }
+                            ast::Field::Fixed {
+                                enum_id: Some(enum_id),
+                                tag_id: Some(tag_id),
+                                ..
+                            } => (format!("{}_{}", enum_id, tag_id), false),
+                            ast::Field::Count { field_id, .. } => {
+                                (format!("p->{}_count", sized_name(field_id)), width < 64)
+                            }
+                            ast::Field::Size { field_id, .. } => {
+                                let name = sized_name(field_id);
+                                let value = match targets[field_id.as_str()] {
+                                    1 if field_id.starts_with('_') => format!("p->{}_size", name),
+                                    1 => format!("p->{}_count", name),
+                                    bytes => format!("(p->{}_count * {})", name, bytes),
+                                };
+                                (value, width < 64)
+                            }
+                            _ => continue,
+                        };
+                        if checked {
+                            writeln!(out, "    if ({} > {:#x}) {{", value, max_value(width))
+                                .unwrap();
+                            writeln!(out, "        return false;").unwrap();
+                            writeln!(out, "    }}").unwrap();
+                        }
+                        if *shift == 0 {
+                            values.push(format!("(uint64_t){}", value));
+                        } else {
+                            values.push(format!("((uint64_t){} << {})", value, shift));
+                        }
+                    }
+                    if values.is_empty() {
+                        values.push("0".to_owned());
+                    }
+                    writeln!(
+                        out,
+                        "    pdl_put_uint_{}(buf + offset, {}, {});",
+                        e,
+                        bytes,
+                        values.join(" | ")
+                    )
+                    .unwrap();
+                    writeln!(out, "    offset += {};", bytes).unwrap();
+                }
+                Item::Bytes(ast::Field::Array {
+                    id: array_id,
+                    width: Some(width),
+                    size: Some(size),
+                    ..
+                }) => {
+                    let bytes = width / 8;
+                    writeln!(out, "    for (size_t i = 0; i < {}; i++) {{", size).unwrap();
+                    if *width < c_type_width(*width) {
+                        writeln!(
+                            out,
+                            "        if (p->{}[i] > {:#x}) {{",
+                            array_id,
+                            max_value(*width)
+                        )
+                        .unwrap();
+                        writeln!(out, "            return false;").unwrap();
+                        writeln!(out, "        }}").unwrap();
+                    }
+                    writeln!(
+                        out,
+                        "        pdl_put_uint_{}(buf + offset + i * {}, {}, p->{}[i]);",
+                        e, bytes, bytes, array_id
+                    )
+                    .unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out, "    offset += {};", size * bytes).unwrap();
+                }
+                Item::Bytes(ast::Field::Array { id: array_id, width: Some(width), .. })
+                    if *width > 8 =>
+                {
+                    let bytes = width / 8;
+                    writeln!(out, "    for (size_t i = 0; i < p->{}_count; i++) {{", array_id)
+                        .unwrap();
+                    writeln!(
+                        out,
+                        "        {} value = {}_get_{}(p, i);",
+                        c_type(*width),
+                        id,
+                        array_id
+                    )
+                    .unwrap();
+                    if *width < c_type_width(*width) {
+                        writeln!(out, "        if (value > {:#x}) {{", max_value(*width)).unwrap();
+                        writeln!(out, "            return false;").unwrap();
+                        writeln!(out, "        }}").unwrap();
+                    }
+                    writeln!(
+                        out,
+                        "        pdl_put_uint_{}(buf + offset + i * {}, {}, value);",
+                        e, bytes, bytes
+                    )
+                    .unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out, "    offset += p->{}_count * {};", array_id, bytes).unwrap();
+                }
+                Item::Bytes(field) => {
+                    let (name, count, size) = match field {
+                        ast::Field::Array { id, .. } => {
+                            let count = format!("p->{}_count", id);
+                            (id.as_str(), count.clone(), count)
+                        }
+                        ast::Field::Payload { .. } => {
+                            ("payload", "p->payload_size".to_owned(), "p->payload_size".to_owned())
+                        }
+                        ast::Field::Body { .. } => {
+                            ("body", "p->body_size".to_owned(), "p->body_size".to_owned())
+                        }
+                        _ => unreachable!(),
+                    };
+                    writeln!(out, "    if ({} > 0) {{", count).unwrap();
+                    writeln!(out, "        memcpy(buf + offset, p->{}, {});", name, size).unwrap();
+                    writeln!(out, "    }}").unwrap();
+                    writeln!(out, "    offset += {};", size).unwrap();
+                }
+            }
+        }
+        writeln!(out, "    return true;").unwrap();
+        writeln!(out, "}}").unwrap();
+    }
+
+    /// Generate element accessors for variable sized arrays, which
+    /// read the host order elements when set, and decode the
+    /// referenced bytes otherwise.
+    fn generate_accessors(&self, out: &mut String, id: &str, fields: &[ast::Field]) {
+        for field in fields {
+            if let ast::Field::Array { id: array_id, width: Some(width), size: None, .. } = field {
+                let bytes = width / 8;
+                writeln!(out).unwrap();
+                writeln!(
+                    out,
+                    "static inline {} {}_get_{}(const {} *p, size_t index) {{",
+                    c_type(*width),
+                    id,
+                    array_id,
+                    id
+                )
+                .unwrap();
+                if bytes > 1 {
+                    writeln!(out, "    if (p->{}_values != NULL) {{", array_id).unwrap();
+                    writeln!(out, "        return p->{}_values[index];", array_id).unwrap();
+                    writeln!(out, "    }}").unwrap();
+                }
+                writeln!(
+                    out,
+                    "    return ({})pdl_get_uint_{}(p->{} + index * {}, {});",
+                    c_type(*width),
+                    self.endianness,
+                    array_id,
+                    bytes,
+                    bytes
+                )
+                .unwrap();
+                writeln!(out, "}}").unwrap();
+            }
+        }
+    }
+}
+
+/// Generate a C header for the grammar.
+/// Returns an error message for declarations the backend cannot
+/// represent: inheritance, groups, checksums, custom fields, struct
+/// typedefs, and arrays of non byte aligned or non scalar elements.
+pub fn generate(grammar: &ast::Grammar) -> Result<String, String> {
+    let endianness = match grammar.endianness.as_ref().map(|e| &e.value) {
+        Some(ast::EndiannessValue::BigEndian) => "be",
+        _ => "le",
+    };
+    let mut enums = HashMap::new();
+    for decl in &grammar.declarations {
+        if let ast::Decl::Enum { id, width, .. } = decl {
+            // Enumerators must be representable as an int.
+            if *width > 31 {
+                return Err(format!("{}: enums wider than 31 bits are not supported", id));
+            }
+            enums.insert(id.as_str(), *width);
+        }
+    }
+    let generator = Generator { endianness, enums };
+
+    let mut out = String::new();
+    out.push_str(PREAMBLE);
+    out.push_str(if endianness == "le" { LE_HELPERS } else { BE_HELPERS });
+    // Enums are emitted first since they can be used
+    // before their declaration.
+    for decl in &grammar.declarations {
+        if let ast::Decl::Enum { id, tags, .. } = decl {
+            generator.generate_enum(&mut out, id, tags)
+        }
+    }
+    for decl in &grammar.declarations {
+        match decl {
+            ast::Decl::Packet { id, fields, parent_id: None, .. }
+            | ast::Decl::Struct { id, fields, parent_id: None, .. } => {
+                generator.generate_packet(&mut out, id, fields)?
+            }
+            ast::Decl::Packet { id, .. } | ast::Decl::Struct { id, .. } => {
+                return Err(format!("{}: inheritance is not supported", id))
+            }
+            // Groups are only referenced through group fields,
+            // which are rejected by the layout.
+            ast::Decl::Enum { .. }
+            | ast::Decl::Group { .. }
+            | ast::Decl::Checksum { .. }
+            | ast::Decl::CustomField { .. }
+            | ast::Decl::Test { .. } => (),
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::parse_inline;
+
+    fn generate_inline(text: &str) -> Result<String, String> {
+        let mut db = ast::SourceDatabase::new();
+        let grammar =
+            parse_inline(&mut db, "stdin".to_owned(), text.to_owned()).expect("parsing failure");
+        generate(&grammar)
+    }
+
+    #[test]
+    fn test_packet_decl_empty() {
+        let code = generate_inline(
+            r#"
+            little_endian_packets
+            packet Foo {}
+            "#,
+        )
+        .unwrap();
+        assert_eq!(code, include_str!("../../tests/generated/c/packet_decl_empty.h"));
+    }
+
+    #[test]
+    fn test_packet_decl_scalars() {
+        let code = generate_inline(
+            r#"
+            big_endian_packets
+            enum Enum : 4 {
+                A = 1,
+                B = 2,
+            }
+            packet Foo {
+                a: 4,
+                b: Enum,
+                c: 24,
+                _reserved_: 8,
+                _fixed_ = 0x2a: 8,
+                d: 64,
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(code, include_str!("../../tests/generated/c/packet_decl_scalars.h"));
+    }
+
+    #[test]
+    fn test_packet_decl_array() {
+        let code = generate_inline(
+            r#"
+            little_endian_packets
+            packet Foo {
+                _count_(a): 8,
+                a: 16[],
+                b: 8[2],
+                _size_(_payload_): 8,
+                _payload_,
+            }
+            "#,
+        )
+        .unwrap();
+        assert_eq!(code, include_str!("../../tests/generated/c/packet_decl_array.h"));
+    }
+
+    #[test]
+    fn test_enum_declared_after_use() {
+        let code = generate_inline(
+            r#"
+            little_endian_packets
+            packet Foo {
+                b: Enum,
+                _reserved_: 4,
+            }
+            enum Enum : 4 {
+                A = 1,
+            }
+            "#,
+        )
+        .unwrap();
+        let enum_offset = code.find("} Enum;").unwrap();
+        let packet_offset = code.find("} Foo;").unwrap();
+        assert!(enum_offset < packet_offset);
+    }
+
+    #[test]
+    fn test_unsupported_enum_width() {
+        let result = generate_inline(
+            r#"
+            little_endian_packets
+            enum Enum : 32 {
+                A = 0x80000000,
+            }
+            "#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unsupported_inheritance() {
+        let result = generate_inline(
+            r#"
+            little_endian_packets
+            packet Parent { _payload_ }
+            packet Child : Parent { a: 8 }
+            "#,
+        );
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unaligned_array() {
+        let result = generate_inline(
+            r#"
+            little_endian_packets
+            packet Foo {
+                a: 4,
+                b: 8[],
+                c: 4,
+            }
+            "#,
+        );
+        assert!(result.is_err());
+    }
+}
@@ -131,6 +131,9 @@ impl LintDiagnostics {
     }
 }
 
+/// Width of the widest integer type available to the code generators.
+const MAX_SCALAR_WIDTH: usize = 64;
+
 fn bit_width(val: usize) -> usize {
     usize::BITS as usize - val.leading_zeros() as usize
 }
@@ -624,7 +627,13 @@ impl Field {
 }
 
 // Helper for linting an enum declaration.
-fn lint_enum(tags: &[Tag], width: usize, result: &mut LintDiagnostics) {
+fn lint_enum(loc: &SourceRange, tags: &[Tag], width: usize, result: &mut LintDiagnostics) {
+    // Enum values must fit the widest integer type, like
+    // the typedef fields using them.
+    if width > MAX_SCALAR_WIDTH {
+        result.push(width_diagnostic("enum", loc, width))
+    }
+
     let mut local_scope = HashMap::new();
     for tag in tags {
         // Tags must be unique within the scope of the
@@ -1050,6 +1059,27 @@ fn lint_typedef(
     }
 }
 
+// Helper for building width diagnostics.
+fn width_diagnostic(kind: &str, loc: &SourceRange, width: usize) -> Diagnostic<FileId> {
+    Diagnostic::error()
+        .with_message(format!("{} width of {} bits exceeds the maximum width", kind, width))
+        .with_labels(vec![loc
+            .primary()
+            .with_message(format!("expected maximum width of `{}`", MAX_SCALAR_WIDTH))])
+}
+
+// Helper for linting field widths.
+fn lint_width(path: &FieldPath, width: usize, result: &mut LintDiagnostics) {
+    // Scalar values must fit the widest integer type, otherwise
+    // they would be silently truncated by the generated code.
+    if width > MAX_SCALAR_WIDTH {
+        result.push(
+            width_diagnostic("field", path.loc(), width)
+                .with_notes(vec!["hint: split the field or use a byte array".to_owned()]),
+        )
+    }
+}
+
 // Helper for linting a field declaration.
 fn lint_field(
     scope: &Scope,
@@ -1062,21 +1092,29 @@ fn lint_field(
             lint_checksum(scope, packet_scope, field, field_id, result)
         }
         Field::Size { field_id, width, .. } => {
+            lint_width(field, *width, result);
             lint_size(scope, packet_scope, field, field_id, *width, result)
         }
         Field::Count { field_id, width, .. } => {
+            lint_width(field, *width, result);
             lint_count(scope, packet_scope, field, field_id, *width, result)
         }
         Field::Fixed { width, value, enum_id, tag_id, .. } => {
+            if let Some(width) = width {
+                lint_width(field, *width, result)
+            }
             lint_fixed(scope, packet_scope, field, width, value, enum_id, tag_id, result)
         }
         Field::Array { width, type_id, size_modifier, size, .. } => {
+            if let Some(width) = width {
+                lint_width(field, *width, result)
+            }
             lint_array(scope, packet_scope, field, width, type_id, size_modifier, size, result)
         }
         Field::Typedef { type_id, .. } => lint_typedef(scope, packet_scope, field, type_id, result),
+        Field::Scalar { width, .. } => lint_width(field, *width, result),
         Field::Padding { .. }
         | Field::Reserved { .. }
-        | Field::Scalar { .. }
         | Field::Body { .. }
         | Field::Payload { .. } => (),
         Field::Group { .. } => unreachable!(),
@@ -1200,7 +1238,7 @@ impl Decl {
     fn lint<'d>(&'d self, scope: &Scope<'d>, result: &mut LintDiagnostics) {
         match self {
             Decl::Checksum { .. } | Decl::CustomField { .. } => (),
-            Decl::Enum { tags, width, .. } => lint_enum(tags, *width, result),
+            Decl::Enum { loc, tags, width, .. } => lint_enum(loc, tags, *width, result),
             Decl::Packet { id, loc, constraints, parent_id, .. } => {
                 lint_packet(scope, self, id, loc, constraints, parent_id, result)
             }
@@ -1291,4 +1329,62 @@ mod test {
         let result = grammar.lint();
         assert!(!result.diagnostics.is_empty());
     }
+
+    #[test]
+    fn test_scalar_width_exceeded() {
+        let mut db = SourceDatabase::new();
+        let grammar = grammar!(
+            &mut db,
+            r#"
+        little_endian_packets
+        packet Foo { a: 72 }
+        "#
+        );
+        let result = grammar.lint();
+        assert_eq!(result.diagnostics.len(), 1);
+        let diagnostic = &result.diagnostics[0];
+        assert_eq!(diagnostic.message, "field width of 72 bits exceeds the maximum width");
+        assert_eq!(diagnostic.labels.len(), 1);
+        assert_eq!(diagnostic.labels[0].message, "expected maximum width of `64`");
+
+        let loc = match &grammar.declarations[0] {
+            Decl::Packet { fields, .. } => fields[0].loc(),
+            _ => unreachable!(),
+        };
+        assert_eq!(diagnostic.labels[0].range, loc.start.offset..loc.end.offset);
+    }
+
+    #[test]
+    fn test_enum_width_exceeded() {
+        let mut db = SourceDatabase::new();
+        let grammar = grammar!(
+            &mut db,
+            r#"
+        little_endian_packets
+        enum Foo : 72 { A = 1 }
+        "#
+        );
+        let result = grammar.lint();
+        assert_eq!(result.diagnostics.len(), 1);
+        let diagnostic = &result.diagnostics[0];
+        assert_eq!(diagnostic.message, "enum width of 72 bits exceeds the maximum width");
+
+        let loc = grammar.declarations[0].loc();
+        assert_eq!(diagnostic.labels[0].range, loc.start.offset..loc.end.offset);
+    }
+
+    #[test]
+    fn test_scalar_width_maximum() {
+        let mut db = SourceDatabase::new();
+        let grammar = grammar!(
+            &mut db,
+            r#"
+        little_endian_packets
+        enum Bar : 64 { A = 1 }
+        packet Foo { a: 64, _size_(b): 8, b: 64[], c: Bar }
+        "#
+        );
+        let result = grammar.lint();
+        assert!(result.diagnostics.is_empty());
+    }
 }
@@ -4,11 +4,30 @@ use codespan_reporting::term::{self, termcolor};
 use structopt::StructOpt;
 
 mod ast;
+mod backends;
 mod lint;
 mod parser;
 
 use crate::lint::Lintable;
 
+#[derive(Debug)]
+enum OutputFormat {
+    Json,
+    C,
+}
+
+impl std::str::FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        match input.to_lowercase().as_str() {
+            "json" => Ok(Self::Json),
+            "c" => Ok(Self::C),
+            _ => Err(format!("could not parse {:?}, valid options are 'json' and 'c'.", input)),
+        }
+    }
+}
+
 #[derive(Debug, StructOpt)]
 #[structopt(name = "pdl-parser", about = "Packet Description Language parser tool.")]
 struct Opt {
@@ -16,6 +35,10 @@ struct Opt {
     #[structopt(short, long = "--version")]
     version: bool,
 
+    /// Generate output in this format ("json" or "c").
+    #[structopt(long, default_value = "json")]
+    output_format: OutputFormat,
+
     /// Input file.
     #[structopt(name = "FILE")]
     input_file: String,
@@ -33,7 +56,18 @@ fn main() {
     match parser::parse_file(&mut sources, opt.input_file) {
         Ok(grammar) => {
             let _ = grammar.lint().print(&sources, termcolor::ColorChoice::Always);
-            println!("{}", serde_json::to_string_pretty(&grammar).unwrap())
+            match opt.output_format {
+                OutputFormat::Json => {
+                    println!("{}", serde_json::to_string_pretty(&grammar).unwrap())
+                }
+                OutputFormat::C => match backends::c::generate(&grammar) {
+                    Ok(code) => print!("{}", code),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                        std::process::exit(1)
+                    }
+                },
+            }
         }
         Err(err) => {
             let writer = termcolor::StandardStream::stderr(termcolor::ColorChoice::Always);
@@ -0,0 +1,34 @@
+// Round trip test of variable sized arrays with big endian elements.
+
+#include <assert.h>
+#include <stdio.h>
+
+#include "array_be.h"
+
+int main(void) {
+    static const uint16_t values[] = {0x0102, 0x0304};
+    static const uint8_t expected[] = {0x02, 0x01, 0x02, 0x03, 0x04};
+
+    // Serialize host order elements.
+    Foo foo = {.a = NULL, .a_values = values, .a_count = 2};
+    uint8_t buf[sizeof(expected)];
+    assert(Foo_size(&foo) == sizeof(expected));
+    assert(Foo_serialize(&foo, buf, sizeof(buf)));
+    assert(memcmp(buf, expected, sizeof(expected)) == 0);
+
+    // Parse the encoded bytes back.
+    Foo parsed;
+    assert(Foo_parse(buf, sizeof(buf), &parsed));
+    assert(parsed.a_values == NULL);
+    assert(parsed.a_count == 2);
+    assert(Foo_get_a(&parsed, 0) == 0x0102);
+    assert(Foo_get_a(&parsed, 1) == 0x0304);
+
+    // Serialize the parsed packet again.
+    uint8_t copy[sizeof(expected)];
+    assert(Foo_serialize(&parsed, copy, sizeof(copy)));
+    assert(memcmp(copy, expected, sizeof(expected)) == 0);
+
+    printf("PASS\n");
+    return 0;
+}
@@ -0,0 +1,116 @@
+// File generated by the PDL C backend.
+// /!\ Do not edit by hand.
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#ifndef PDL_UINT_LE_HELPERS
+#define PDL_UINT_LE_HELPERS
+
+static inline uint64_t pdl_get_uint_le(const uint8_t *buf, size_t n) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < n; i++) {
+        value |= (uint64_t)buf[i] << (8 * i);
+    }
+    return value;
+}
+
+static inline void pdl_put_uint_le(uint8_t *buf, size_t n, uint64_t value) {
+    for (size_t i = 0; i < n; i++) {
+        buf[i] = (uint8_t)(value >> (8 * i));
+    }
+}
+
+#endif  // PDL_UINT_LE_HELPERS
+
+typedef struct {
+    const uint8_t *a;
+    const uint16_t *a_values;
+    size_t a_count;
+    uint8_t b[2];
+    const uint8_t *payload;
+    size_t payload_size;
+} Foo;
+
+static inline bool Foo_parse(const uint8_t *buf, size_t len, Foo *out) {
+    size_t offset = 0;
+    if (len - offset < 1) {
+        return false;
+    }
+    uint64_t chunk0 = pdl_get_uint_le(buf + offset, 1);
+    size_t a_count = (size_t)chunk0;
+    offset += 1;
+    if (a_count > (len - offset) / 2) {
+        return false;
+    }
+    out->a = buf + offset;
+    out->a_count = a_count;
+    offset += a_count * 2;
+    out->a_values = NULL;
+    if (len - offset < 2) {
+        return false;
+    }
+    for (size_t i = 0; i < 2; i++) {
+        out->b[i] = (uint8_t)pdl_get_uint_le(buf + offset + i * 1, 1);
+    }
+    offset += 2;
+    if (len - offset < 1) {
+        return false;
+    }
+    uint64_t chunk3 = pdl_get_uint_le(buf + offset, 1);
+    size_t payload_size = (size_t)chunk3;
+    offset += 1;
+    if (payload_size > len - offset) {
+        return false;
+    }
+    out->payload = buf + offset;
+    out->payload_size = payload_size;
+    offset += payload_size;
+    return offset == len;
+}
+
+static inline size_t Foo_size(const Foo *p) {
+    return 4 + p->a_count * 2 + p->payload_size;
+}
+
+static inline uint16_t Foo_get_a(const Foo *p, size_t index) {
+    if (p->a_values != NULL) {
+        return p->a_values[index];
+    }
+    return (uint16_t)pdl_get_uint_le(p->a + index * 2, 2);
+}
+
+static inline bool Foo_serialize(const Foo *p, uint8_t *buf, size_t len) {
+    size_t offset = 0;
+    if (len < Foo_size(p)) {
+        return false;
+    }
+    if (p->a_count > 0xff) {
+        return false;
+    }
+    pdl_put_uint_le(buf + offset, 1, (uint64_t)p->a_count);
+    offset += 1;
+    for (size_t i = 0; i < p->a_count; i++) {
+        uint16_t value = Foo_get_a(p, i);
+        pdl_put_uint_le(buf + offset + i * 2, 2, value);
+    }
+    offset += p->a_count * 2;
+    for (size_t i = 0; i < 2; i++) {
+        pdl_put_uint_le(buf + offset + i * 1, 1, p->b[i]);
+    }
+    offset += 2;
+    if (p->payload_size > 0xff) {
+        return false;
+    }
+    pdl_put_uint_le(buf + offset, 1, (uint64_t)p->payload_size);
+    offset += 1;
+    if (p->payload_size > 0) {
+        memcpy(buf + offset, p->payload, p->payload_size);
+    }
+    offset += p->payload_size;
+    return true;
+}
@@ -0,0 +1,50 @@
+// File generated by the PDL C backend.
+// /!\ Do not edit by hand.
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#ifndef PDL_UINT_LE_HELPERS
+#define PDL_UINT_LE_HELPERS
+
+static inline uint64_t pdl_get_uint_le(const uint8_t *buf, size_t n) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < n; i++) {
+        value |= (uint64_t)buf[i] << (8 * i);
+    }
+    return value;
+}
+
+static inline void pdl_put_uint_le(uint8_t *buf, size_t n, uint64_t value) {
+    for (size_t i = 0; i < n; i++) {
+        buf[i] = (uint8_t)(value >> (8 * i));
+    }
+}
+
+#endif  // PDL_UINT_LE_HELPERS
+
+typedef struct {
+    uint8_t _unused;
+} Foo;
+
+static inline bool Foo_parse(const uint8_t *buf, size_t len, Foo *out) {
+    (void)buf;
+    (void)out;
+    return len == 0;
+}
+
+static inline size_t Foo_size(const Foo *p) {
+    (void)p;
+    return 0;
+}
+
+static inline bool Foo_serialize(const Foo *p, uint8_t *buf, size_t len) {
+    (void)p;
+    (void)buf;
+    (void)len;
+    return true;
+}
@@ -0,0 +1,121 @@
+// File generated by the PDL C backend.
+// /!\ Do not edit by hand.
+
+#pragma once
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <string.h>
+
+#ifndef PDL_UINT_BE_HELPERS
+#define PDL_UINT_BE_HELPERS
+
+static inline uint64_t pdl_get_uint_be(const uint8_t *buf, size_t n) {
+    uint64_t value = 0;
+    for (size_t i = 0; i < n; i++) {
+        value = (value << 8) | buf[i];
+    }
+    return value;
+}
+
+static inline void pdl_put_uint_be(uint8_t *buf, size_t n, uint64_t value) {
+    for (size_t i = 0; i < n; i++) {
+        buf[n - 1 - i] = (uint8_t)(value >> (8 * i));
+    }
+}
+
+#endif  // PDL_UINT_BE_HELPERS
+
+typedef enum {
+    Enum_A = 0x1,
+    Enum_B = 0x2,
+} Enum;
+
+static inline bool Enum_is_valid(uint64_t value) {
+    switch (value) {
+    case 0x1:
+    case 0x2:
+        return true;
+    default:
+        return false;
+    }
+}
+
+typedef struct {
+    uint8_t a;
+    Enum b;
+    uint32_t c;
+    uint64_t d;
+} Foo;
+
+static inline bool Foo_parse(const uint8_t *buf, size_t len, Foo *out) {
+    size_t offset = 0;
+    if (len - offset < 1) {
+        return false;
+    }
+    uint64_t chunk0 = pdl_get_uint_be(buf + offset, 1);
+    out->a = (uint8_t)(chunk0 & 0xf);
+    if (!Enum_is_valid((chunk0 >> 4))) {
+        return false;
+    }
+    out->b = (Enum)(chunk0 >> 4);
+    offset += 1;
+    if (len - offset < 3) {
+        return false;
+    }
+    uint64_t chunk1 = pdl_get_uint_be(buf + offset, 3);
+    out->c = (uint32_t)chunk1;
+    offset += 3;
+    if (len - offset < 1) {
+        return false;
+    }
+    offset += 1;
+    if (len - offset < 1) {
+        return false;
+    }
+    uint64_t chunk3 = pdl_get_uint_be(buf + offset, 1);
+    if (chunk3 != 0x2a) {
+        return false;
+    }
+    offset += 1;
+    if (len - offset < 8) {
+        return false;
+    }
+    uint64_t chunk4 = pdl_get_uint_be(buf + offset, 8);
+    out->d = (uint64_t)chunk4;
+    offset += 8;
+    return offset == len;
+}
+
+static inline size_t Foo_size(const Foo *p) {
+    (void)p;
+    return 14;
+}
+
+static inline bool Foo_serialize(const Foo *p, uint8_t *buf, size_t len) {
+    size_t offset = 0;
+    if (len < Foo_size(p)) {
+        return false;
+    }
+    if (p->a > 0xf) {
+        return false;
+    }
+    if (!Enum_is_valid(p->b)) {
+        return false;
+    }
+    pdl_put_uint_be(buf + offset, 1, (uint64_t)p->a | ((uint64_t)p->b << 4));
+    offset += 1;
+    if (p->c > 0xffffff) {
+        return false;
+    }
+    pdl_put_uint_be(buf + offset, 3, (uint64_t)p->c);
+    offset += 3;
+    pdl_put_uint_be(buf + offset, 1, 0);
+    offset += 1;
+    pdl_put_uint_be(buf + offset, 1, (uint64_t)0x2a);
+    offset += 1;
+    pdl_put_uint_be(buf + offset, 8, (uint64_t)p->d);
+    offset += 8;
+    return true;
+}